@@ -11,7 +11,7 @@ async fn main() {
         HandleStruct {},
         args[1].parse().unwrap(),
         "justabot".to_string(),
-        false,
+        ConnectOptions::new(false),
     )
     .await
     .unwrap();
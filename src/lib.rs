@@ -11,18 +11,21 @@ use std::sync::Arc;
 use tokio::net::TcpStream;
 use tokio::sync::Mutex;
 use tokio::time::{delay_for, Duration};
-use tokio_tls::{TlsConnector, TlsStream};
 use tokio_util::codec::{Decoder, Framed};
 
 pub mod prelude;
+pub mod tls;
 pub mod util;
 
+use tls::MumbleTlsStream;
+pub use tls::{ConnectOptions, TlsBackend};
+
 #[derive(Debug)]
 /// The central client struct, responsible for connecting, preprocessing packets
 /// for the `handler` and keeping user information up to date.
 pub struct Client<T> {
     pub sender: Arc<Mutex<Sender>>,
-    receiver: SplitStream<Framed<TlsStream<TcpStream>, ClientControlCodec>>,
+    receiver: SplitStream<Framed<MumbleTlsStream, ClientControlCodec>>,
     handler: T,
     info: ClientInfo,
 }
@@ -43,14 +46,30 @@ pub enum Error {
     /// Might be thrown whenever interacting with the network somehow.
     Network(tokio::io::Error),
     /// Might be thrown during the initial TLS connection or if something
-    /// TLS related goes wrong while sending a packet.
+    /// TLS related goes wrong while sending a packet, when using the
+    /// `native-tls` backend.
     Tls(native_tls::Error),
+    /// Might be thrown during the initial TLS connection when using the
+    /// `rustls` backend.
+    RustlsTls(std::io::Error),
+    /// Thrown when the server's certificate is rejected: with either backend
+    /// when it doesn't match `pinned_certificate`, and with `rustls` also when
+    /// it can't be verified against the trusted roots (including the
+    /// configured extra root certificate). With `native-tls`, a certificate
+    /// that can't be verified against the trusted roots is reported as `Tls`.
+    CertificateVerification(String),
+    /// Thrown when the TLS configuration supplied via
+    /// [`ConnectOptions`](crate::ConnectOptions) is invalid or incomplete,
+    /// e.g. an `extra_root_certificate` that isn't valid DER, or a
+    /// `TlsBackend::Rustls` used without `server_name` or a
+    /// `pinned_certificate` to verify against.
+    InvalidCertificate(String),
 }
 
 /// A convenience type for the `SplitSink` we use in order to communicate iwth the
 /// server.
 pub type Sender =
-    SplitSink<Framed<TlsStream<TcpStream>, ClientControlCodec>, ControlPacket<Serverbound>>;
+    SplitSink<Framed<MumbleTlsStream, ClientControlCodec>, ControlPacket<Serverbound>>;
 /// A convenience type for all packets we receive from the server.
 pub type Packet = ControlPacket<Clientbound>;
 
@@ -71,26 +90,19 @@ where
     T: Handler,
 {
     /// Create a new client and attempt connect it to `host` as `username`.
-    /// `verify_certificate` determines wether the server's SSL certificate
-    /// gets verified or not.
+    /// `connect_options` determines which TLS backend is used and how the
+    /// server's certificate is verified, see [`ConnectOptions`].
     pub async fn new(
         mut handler: T,
         host: SocketAddr,
         username: String,
-        verify_certificate: bool,
+        connect_options: ConnectOptions,
     ) -> Result<Self> {
         info!("Connecting");
         let stream = TcpStream::connect(&host).await.map_err(Error::Network)?;
 
-        let mut builder = native_tls::TlsConnector::builder();
-        builder.danger_accept_invalid_certs(!verify_certificate);
-        let connector: TlsConnector = builder.build().unwrap().into();
-
         debug!("Opening TLS stream");
-        let tls_stream = connector
-            .connect(&host.ip().to_string(), stream)
-            .await
-            .map_err(Error::Tls)?;
+        let tls_stream = tls::connect(&connect_options, host, stream).await?;
 
         let (mut sender, mut receiver) = ClientControlCodec::new().framed(tls_stream).split();
 
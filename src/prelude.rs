@@ -1,5 +1,8 @@
 //! Contains most if not all imports that might be interesting for a consumer of this library.
 pub use crate::util::*;
-pub use crate::{Channel, Client, ClientInfo, Error, Handler, Packet, Result, Sender, SenderExt};
+pub use crate::{
+    Channel, Client, ClientInfo, ConnectOptions, Error, Handler, Packet, Result, Sender, SenderExt,
+    TlsBackend,
+};
 pub use async_trait::async_trait;
 pub use mumble_protocol::control::msgs;